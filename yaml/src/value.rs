@@ -6,30 +6,126 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::hash::{Hash, Hasher};
 use std::mem;
 
 use dtoa;
 use linked_hash_map::LinkedHashMap;
 use serde::{self, Serialize, Deserialize};
-use yaml_rust::Yaml;
+use yaml_rust::{Yaml, YamlLoader};
+use yaml_rust::parser::{Event, EventReceiver, Parser};
+use yaml_rust::scanner::{TScalarStyle, TokenType};
 
-use super::{Error, Deserializer, Serializer};
+use super::{Error, Serializer};
 
 #[derive(Clone, PartialOrd, Debug)]
 pub enum Value {
     Null,
     Bool(bool),
     I64(i64),
-    F64(f64),
+    F64(OrderedF64),
     String(String),
     Sequence(Sequence),
     Mapping(Mapping),
+    /// A value carrying an explicit YAML tag (`!Variant payload`), used to
+    /// drive order-independent enum dispatch in the `Deserializer` impl.
+    ///
+    /// Two limitations of the underlying `yaml_rust` are intentional and
+    /// surfaced here rather than discovered at runtime:
+    ///
+    /// * **Collection tags are not captured.** `yaml_rust`'s event stream
+    ///   exposes tags only on scalar nodes, so `!Foo {…}` / `!Foo [...]` load
+    ///   as a plain `Mapping`/`Sequence` with the tag dropped. Only a tag on a
+    ///   scalar (`!Foo bar`) produces a `Tagged`.
+    /// * **Tags are not re-emitted.** `yaml_rust`'s `Yaml` has no tag node and
+    ///   its emitter cannot write one, so serializing a `Tagged` (or an enum
+    ///   variant) produces the externally-tagged `{tag: value}` map, not a
+    ///   `!Variant` tag. The enum round-trips through that shape; the surface
+    ///   tag syntax does not.
+    Tagged(String, Box<Value>),
 }
 
 pub type Sequence = Vec<Value>;
 pub type Mapping = LinkedHashMap<Value, Value>;
 
+/// A totally-ordered wrapper around `f64`, used as the payload of
+/// [`Value::F64`] so that a `Value` is a sound key in a `Mapping` and in
+/// ordered collections.
+///
+/// The raw `f64` comparison and hash are not self-consistent: `-0.0 == 0.0`
+/// yet they have different bits, and `NaN` is unequal to itself. This newtype
+/// canonicalizes the stored value before hashing or comparing — `-0.0` folds
+/// to `0.0` and every `NaN` to one representative — and orders `NaN` greater
+/// than all other values so that `Ord` agrees with `Eq`.
+#[derive(Clone, Copy, Debug)]
+pub struct OrderedF64(f64);
+
+impl OrderedF64 {
+    pub fn new(value: f64) -> Self {
+        OrderedF64(value)
+    }
+
+    pub fn get(self) -> f64 {
+        self.0
+    }
+
+    /// The canonical bit pattern used for equality and hashing: `-0.0` is
+    /// folded to `0.0` and every `NaN` to a single representative, so equal
+    /// values always share a hash bucket.
+    fn canonical_bits(self) -> u64 {
+        let normalized = if self.0.is_nan() {
+            ::std::f64::NAN
+        } else if self.0 == 0.0 {
+            0.0
+        } else {
+            self.0
+        };
+        unsafe { mem::transmute::<f64, u64>(normalized) }
+    }
+}
+
+impl From<f64> for OrderedF64 {
+    fn from(value: f64) -> Self {
+        OrderedF64(value)
+    }
+}
+
+impl PartialEq for OrderedF64 {
+    fn eq(&self, other: &OrderedF64) -> bool {
+        self.canonical_bits() == other.canonical_bits()
+    }
+}
+
+impl Eq for OrderedF64 {}
+
+impl PartialOrd for OrderedF64 {
+    fn partial_cmp(&self, other: &OrderedF64) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OrderedF64 {
+    fn cmp(&self, other: &OrderedF64) -> Ordering {
+        self.0.partial_cmp(&other.0).unwrap_or_else(|| {
+            // A `None` from `partial_cmp` means a `NaN` is involved; order it
+            // greater than everything and equal to itself.
+            match (self.0.is_nan(), other.0.is_nan()) {
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                _ => Ordering::Equal,
+            }
+        })
+    }
+}
+
+impl Hash for OrderedF64 {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.canonical_bits().hash(state);
+    }
+}
+
 /// Shortcut function to encode a `T` into a YAML `Value`.
 ///
 /// ```rust
@@ -52,12 +148,10 @@ pub fn to_value<T: ?Sized>(value: &T) -> Value
 /// let val = Value::String("foo".to_owned());
 /// assert_eq!("foo", from_value::<String>(val).unwrap());
 /// ```
-pub fn from_value<T>(value: Value) -> Result<T, Error>
+pub fn from_value<T>(mut value: Value) -> Result<T, Error>
     where T: Deserialize,
 {
-    let yaml = value.into();
-    let mut de = Deserializer::new(&yaml);
-    Deserialize::deserialize(&mut de)
+    Deserialize::deserialize(&mut value)
 }
 
 impl Value {
@@ -97,7 +191,7 @@ impl Value {
 
     pub fn as_f64(&self) -> Option<f64> {
         match *self {
-            Value::F64(i) => Some(i),
+            Value::F64(f) => Some(f.get()),
             _ => None,
         }
     }
@@ -148,32 +242,262 @@ impl Value {
             _ => None,
         }
     }
+
+    pub fn is_tagged(&self) -> bool {
+        self.as_tagged().is_some()
+    }
+
+    pub fn as_tagged(&self) -> Option<(&str, &Value)> {
+        match *self {
+            Value::Tagged(ref tag, ref value) => Some((tag, value)),
+            _ => None,
+        }
+    }
+}
+
+/// Parse a YAML document into a `Value`, resolving anchors, aliases and `<<`
+/// merge keys.
+///
+/// Anchor ids are only available at the `yaml_rust` event level — the `Yaml`
+/// tree produced by `YamlLoader` drops them, keeping only an opaque
+/// `Yaml::Alias(id)` on the *referencing* node — so anchor resolution cannot
+/// be done from `From<Yaml>` and has to run over the parser's event stream.
+/// This is the anchor-aware entry point; `From<Yaml>` remains a lossy bridge
+/// for already-built `Yaml` trees.
+///
+/// The source must hold a single document. An empty stream yields
+/// [`Value::Null`]; a multi-document stream (`---`-separated) is an error
+/// rather than silently keeping only the first document.
+pub fn from_str(source: &str) -> Result<Value, Error> {
+    let mut loader = Loader {
+        docs: Vec::new(),
+        doc_stack: Vec::new(),
+        key_stack: Vec::new(),
+        anchors: BTreeMap::new(),
+    };
+    let mut parser = Parser::new(source.chars());
+    try!(parser.load(&mut loader, true).map_err(|e| {
+        <Error as serde::de::Error>::custom(format!("{}", e))
+    }));
+    let mut docs = loader.docs.into_iter();
+    let first = docs.next().unwrap_or(Value::Null);
+    if docs.next().is_some() {
+        return Err(<Error as serde::de::Error>::custom(
+            "expected a single YAML document, found more than one"));
+    }
+    Ok(first)
+}
+
+/// Builds a `Value` from a `yaml_rust` event stream, keeping an `anchors` map
+/// from anchor id to the already-built `Value` so that each `*alias` (and the
+/// `<<` merge idiom built on it) expands to a clone of its `&anchor`.
+struct Loader {
+    docs: Vec<Value>,
+    doc_stack: Vec<(Value, usize)>,
+    key_stack: Vec<Option<Value>>,
+    anchors: BTreeMap<usize, Value>,
+}
+
+impl Loader {
+    fn insert_new_node(&mut self, node: (Value, usize)) {
+        if node.1 > 0 {
+            self.anchors.insert(node.1, node.0.clone());
+        }
+        if self.doc_stack.is_empty() {
+            self.doc_stack.push(node);
+            return;
+        }
+        let parent_is_seq = match self.doc_stack.last().unwrap().0 {
+            Value::Sequence(_) => true,
+            _ => false,
+        };
+        if parent_is_seq {
+            if let (Value::Sequence(ref mut seq), _) = *self.doc_stack.last_mut().unwrap() {
+                seq.push(node.0);
+            }
+        } else if self.key_stack.last().unwrap().is_none() {
+            *self.key_stack.last_mut().unwrap() = Some(node.0);
+        } else {
+            let key = self.key_stack.last_mut().unwrap().take().unwrap();
+            if let (Value::Mapping(ref mut map), _) = *self.doc_stack.last_mut().unwrap() {
+                if key == Value::String("<<".to_owned()) {
+                    merge_into(map, node.0);
+                } else {
+                    map.insert(key, node.0);
+                }
+            }
+        }
+    }
+}
+
+impl EventReceiver for Loader {
+    fn on_event(&mut self, event: Event) {
+        match event {
+            Event::DocumentStart | Event::Nothing | Event::StreamStart |
+            Event::StreamEnd => {}
+            Event::DocumentEnd => {
+                match self.doc_stack.pop() {
+                    Some((node, _)) => self.docs.push(node),
+                    None => self.docs.push(Value::Null),
+                }
+            }
+            Event::SequenceStart(aid) => {
+                self.doc_stack.push((Value::Sequence(Sequence::new()), aid));
+            }
+            Event::SequenceEnd => {
+                let node = self.doc_stack.pop().unwrap();
+                self.insert_new_node(node);
+            }
+            Event::MappingStart(aid) => {
+                self.doc_stack.push((Value::Mapping(Mapping::new()), aid));
+                self.key_stack.push(None);
+            }
+            Event::MappingEnd => {
+                self.key_stack.pop();
+                let node = self.doc_stack.pop().unwrap();
+                self.insert_new_node(node);
+            }
+            Event::Scalar(value, style, aid, tag) => {
+                self.insert_new_node((resolve_scalar(value, style, tag), aid));
+            }
+            Event::Alias(aid) => {
+                let node = self.anchors.get(&aid).cloned().unwrap_or(Value::Null);
+                self.insert_new_node((node, 0));
+            }
+        }
+    }
+}
+
+/// Resolve a scalar to its typed `Value`, honouring an explicit tag.
+///
+/// A core-schema tag (`!!str`, `!!int`, …) forces the corresponding type; an
+/// application tag (`!Variant`) produces a [`Value::Tagged`] carrying the
+/// resolved payload, giving order-independent enum dispatch. Note that
+/// `yaml_rust`'s event stream only surfaces tags on *scalar* nodes — its
+/// `MappingStart`/`SequenceStart` events carry an anchor id but no tag — so a
+/// tag on a collection (`!Variant {…}` / `!Variant [...]`) cannot be captured
+/// against this version of the parser and resolves to a plain (untagged)
+/// `Mapping`/`Sequence`.
+fn resolve_scalar(value: String, style: TScalarStyle, tag: Option<TokenType>) -> Value {
+    if let Some(TokenType::Tag(handle, suffix)) = tag {
+        if handle == "!!" {
+            return resolve_core_tag(&suffix, value, style);
+        } else if handle == "!" && !suffix.is_empty() {
+            return Value::Tagged(suffix, Box::new(resolve_plain(value, style)));
+        }
+    }
+    resolve_plain(value, style)
+}
+
+/// Apply a YAML core-schema tag (the `!!…` suffix) to a scalar.
+fn resolve_core_tag(suffix: &str, value: String, style: TScalarStyle) -> Value {
+    match suffix {
+        "str" => Value::String(value),
+        "null" => Value::Null,
+        "bool" => {
+            match value.as_str() {
+                "true" | "True" | "TRUE" => Value::Bool(true),
+                _ => Value::Bool(false),
+            }
+        }
+        "int" => value.parse::<i64>().map(Value::I64).unwrap_or(Value::String(value)),
+        "float" => {
+            value.parse::<f64>()
+                 .map(|f| Value::F64(OrderedF64::new(f)))
+                 .unwrap_or_else(|_| Value::String(value))
+        }
+        _ => resolve_plain(value, style),
+    }
+}
+
+/// Resolve an untagged scalar, applying the YAML core schema: non-plain
+/// (quoted or block) scalars stay strings, plain ones are resolved by
+/// `yaml_rust` itself.
+///
+/// The typing is delegated to `yaml_rust::Yaml::from_str` — the exact routine
+/// `YamlLoader` runs for plain untagged scalars — rather than hand-rolled, so
+/// the event loader and the `From<Yaml>` bridge (which consumes `YamlLoader`
+/// output) agree on every scalar by construction. Whatever core-schema types
+/// `yaml_rust` recognizes, such as hex/octal ints (`0x1F`, `0o17`), are honored
+/// identically on both paths instead of one falling back to `Value::String`.
+fn resolve_plain(value: String, style: TScalarStyle) -> Value {
+    if style != TScalarStyle::Plain {
+        return Value::String(value);
+    }
+    Value::from(Yaml::from_str(&value))
 }
 
 impl From<Yaml> for Value {
     fn from(yaml: Yaml) -> Self {
         match yaml {
-            Yaml::Real(f) => Value::F64(f.parse().unwrap()),
+            Yaml::Real(ref f) => Value::F64(OrderedF64::new(parse_real(f))),
             Yaml::Integer(i) => Value::I64(i),
             Yaml::String(s) => Value::String(s),
             Yaml::Boolean(b) => Value::Bool(b),
-            Yaml::Array(array) =>  {
-                Value::Sequence(array.into_iter()
-                                     .map(Into::into)
-                                     .collect())
+            Yaml::Array(array) => {
+                Value::Sequence(array.into_iter().map(Into::into).collect())
             }
             Yaml::Hash(hash) => {
-                Value::Mapping(hash.into_iter()
-                                   .map(|(k, v)| (k.into(), v.into()))
-                                   .collect())
+                let mut mapping = Mapping::new();
+                for (k, v) in hash {
+                    let key = Value::from(k);
+                    let value = Value::from(v);
+                    if key == Value::String("<<".to_owned()) {
+                        merge_into(&mut mapping, value);
+                    } else {
+                        mapping.insert(key, value);
+                    }
+                }
+                Value::Mapping(mapping)
             }
-            Yaml::Alias(_) => panic!("alias unsupported"),
+            // A raw `Yaml` tree carries no anchor table, so an alias cannot be
+            // expanded on this bridge. Fail loudly rather than degrading to
+            // `Null` and silently dropping the referenced data — callers that
+            // need anchor resolution must load through [`from_str`], which
+            // expands aliases off the parser's event stream. (The crate-level
+            // `from_str`/`from_reader` in the parent module should route `Value`
+            // construction through that loader for exactly this reason.)
+            Yaml::Alias(_) => panic!("cannot resolve a YAML alias through From<Yaml>; load the document with value::from_str"),
             Yaml::Null => Value::Null,
             Yaml::BadValue => panic!("bad value"),
         }
     }
 }
 
+/// Parse a `yaml_rust` `Yaml::Real` payload into an `f64`, accepting the YAML
+/// spellings of the non-finite floats (`.inf`, `-.inf`, `.nan`) that Rust's
+/// own `f64` parser rejects, so a `Real` carrying one never panics the bridge.
+fn parse_real(text: &str) -> f64 {
+    match text {
+        ".inf" | ".Inf" | ".INF" | "+.inf" | "+.Inf" | "+.INF" => ::std::f64::INFINITY,
+        "-.inf" | "-.Inf" | "-.INF" => ::std::f64::NEG_INFINITY,
+        ".nan" | ".NaN" | ".NAN" => ::std::f64::NAN,
+        _ => text.parse().unwrap_or(::std::f64::NAN),
+    }
+}
+
+/// Splice the entries of a `<<` merge key into `mapping`. The merged value may
+/// be a single mapping or a sequence of mappings (applied in order); in either
+/// case keys already present in `mapping` win over the merged entries, matching
+/// the YAML merge-key semantics.
+fn merge_into(mapping: &mut Mapping, value: Value) {
+    match value {
+        Value::Mapping(source) => {
+            for (k, v) in source {
+                if !mapping.contains_key(&k) {
+                    mapping.insert(k, v);
+                }
+            }
+        }
+        Value::Sequence(seq) => {
+            for item in seq {
+                merge_into(mapping, item);
+            }
+        }
+        _ => {}
+    }
+}
+
 impl From<Value> for Yaml {
     fn from(value: Value) -> Self {
         match value {
@@ -182,7 +506,7 @@ impl From<Value> for Yaml {
             Value::I64(i) => Yaml::Integer(i),
             Value::F64(f) => {
                 let mut buf = Vec::new();
-                dtoa::write(&mut buf, f).unwrap();
+                dtoa::write(&mut buf, f.get()).unwrap();
                 Yaml::Real(String::from_utf8(buf).unwrap())
             }
             Value::String(s) => Yaml::String(s),
@@ -196,6 +520,12 @@ impl From<Value> for Yaml {
                               .map(|(k, v)| (k.into(), v.into()))
                               .collect())
             }
+            // Known limitation: `yaml_rust`'s `Yaml` has no node type for an
+            // explicit tag, so converting through the `Yaml` bridge (as any
+            // `Value -> Yaml -> String` emit path does) drops the tag entirely.
+            // The tag only survives the in-memory `Serialize`/`deserialize_enum`
+            // path; a full parse -> emit cycle does not preserve it.
+            Value::Tagged(_, value) => (*value).into(),
         }
     }
 }
@@ -208,10 +538,21 @@ impl Serialize for Value {
             Value::Null => serializer.serialize_unit(),
             Value::Bool(b) => serializer.serialize_bool(b),
             Value::I64(i) => serializer.serialize_i64(i),
-            Value::F64(f) => serializer.serialize_f64(f),
+            Value::F64(f) => serializer.serialize_f64(f.get()),
             Value::String(ref s) => serializer.serialize_str(s),
             Value::Sequence(ref seq) => seq.serialize(serializer),
             Value::Mapping(ref map) => map.serialize(serializer),
+            // `yaml_rust`'s emitter has no way to write an explicit `!tag`, so
+            // a real `!Variant payload` cannot be emitted on any path that ends
+            // at `yaml_rust`. Instead model a tagged value as the externally
+            // tagged `{tag: value}` single-entry map: the *enum* round-trips
+            // (the enum deserializer accepts exactly this shape — see
+            // `deserialize_enum`), even though the surface tag syntax does not.
+            Value::Tagged(ref tag, ref value) => {
+                let mut map = Mapping::new();
+                map.insert(Value::String(tag.clone()), (**value).clone());
+                map.serialize(serializer)
+            }
         }
     }
 }
@@ -246,7 +587,7 @@ impl Deserialize for Value {
             fn visit_f64<E>(&mut self, f: f64) -> Result<Value, E>
                 where E: serde::de::Error
             {
-                Ok(Value::F64(f))
+                Ok(Value::F64(OrderedF64::new(f)))
             }
 
             fn visit_str<E>(&mut self, s: &str) -> Result<Value, E>
@@ -303,26 +644,236 @@ impl Deserialize for Value {
     }
 }
 
+impl serde::Deserializer for Value {
+    type Error = Error;
+
+    fn deserialize<V>(&mut self, mut visitor: V) -> Result<V::Value, Error>
+        where V: serde::de::Visitor
+    {
+        match mem::replace(self, Value::Null) {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(b) => visitor.visit_bool(b),
+            Value::I64(i) => visitor.visit_i64(i),
+            Value::F64(f) => visitor.visit_f64(f.get()),
+            Value::String(s) => visitor.visit_string(s),
+            Value::Sequence(seq) => {
+                let len = seq.len();
+                let mut de = SeqDeserializer {
+                    iter: seq.into_iter(),
+                    len: len,
+                };
+                visitor.visit_seq(&mut de)
+            }
+            Value::Mapping(map) => {
+                let len = map.len();
+                let mut de = MapDeserializer {
+                    iter: map.into_iter(),
+                    value: None,
+                    len: len,
+                };
+                visitor.visit_map(&mut de)
+            }
+            Value::Tagged(_, value) => {
+                let mut inner = *value;
+                serde::Deserializer::deserialize(&mut inner, visitor)
+            }
+        }
+    }
+
+    fn deserialize_option<V>(&mut self, mut visitor: V) -> Result<V::Value, Error>
+        where V: serde::de::Visitor
+    {
+        match *self {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_enum<V>(&mut self,
+                           _name: &str,
+                           _variants: &'static [&'static str],
+                           mut visitor: V)
+                           -> Result<V::Value, Error>
+        where V: serde::de::EnumVisitor
+    {
+        let (variant, value) = match mem::replace(self, Value::Null) {
+            Value::Mapping(map) => {
+                let mut iter = map.into_iter();
+                let (variant, value) = match iter.next() {
+                    Some(pair) => pair,
+                    None => {
+                        return Err(serde::de::Error::invalid_value(
+                            "expected a single-entry map for an enum variant"));
+                    }
+                };
+                if iter.next().is_some() {
+                    return Err(serde::de::Error::invalid_value(
+                        "expected a single-entry map for an enum variant"));
+                }
+                (variant, Some(value))
+            }
+            variant @ Value::String(_) => (variant, None),
+            Value::Tagged(tag, value) => (Value::String(tag), Some(*value)),
+            _ => {
+                return Err(serde::de::Error::invalid_value(
+                    "expected a string or single-entry map for an enum variant"));
+            }
+        };
+        visitor.visit(VariantDeserializer {
+            variant: variant,
+            value: value,
+        })
+    }
+
+    forward_to_deserialize! {
+        bool usize u8 u16 u32 u64 isize i8 i16 i32 i64 f32 f64 char str string
+        unit seq seq_fixed_size bytes map unit_struct newtype_struct tuple_struct
+        struct struct_field tuple ignored_any
+    }
+}
+
+struct SeqDeserializer {
+    iter: <Sequence as IntoIterator>::IntoIter,
+    len: usize,
+}
+
+impl serde::de::SeqVisitor for SeqDeserializer {
+    type Error = Error;
+
+    fn visit<T>(&mut self) -> Result<Option<T>, Error>
+        where T: Deserialize
+    {
+        match self.iter.next() {
+            Some(mut value) => {
+                self.len -= 1;
+                Ok(Some(try!(Deserialize::deserialize(&mut value))))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn end(&mut self) -> Result<(), Error> {
+        if self.len == 0 {
+            Ok(())
+        } else {
+            Err(serde::de::Error::invalid_length(self.len))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+struct MapDeserializer {
+    iter: <Mapping as IntoIterator>::IntoIter,
+    value: Option<Value>,
+    len: usize,
+}
+
+impl serde::de::MapVisitor for MapDeserializer {
+    type Error = Error;
+
+    fn visit_key<K>(&mut self) -> Result<Option<K>, Error>
+        where K: Deserialize
+    {
+        match self.iter.next() {
+            Some((mut key, value)) => {
+                self.len -= 1;
+                self.value = Some(value);
+                Ok(Some(try!(Deserialize::deserialize(&mut key))))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn visit_value<V>(&mut self) -> Result<V, Error>
+        where V: Deserialize
+    {
+        match self.value.take() {
+            Some(mut value) => Deserialize::deserialize(&mut value),
+            None => Err(serde::de::Error::end_of_stream()),
+        }
+    }
+
+    fn end(&mut self) -> Result<(), Error> {
+        if self.len == 0 {
+            Ok(())
+        } else {
+            Err(serde::de::Error::invalid_length(self.len))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+struct VariantDeserializer {
+    variant: Value,
+    value: Option<Value>,
+}
+
+impl serde::de::VariantVisitor for VariantDeserializer {
+    type Error = Error;
+
+    fn visit_variant<V>(&mut self) -> Result<V, Error>
+        where V: Deserialize
+    {
+        Deserialize::deserialize(&mut self.variant)
+    }
+
+    fn visit_unit(&mut self) -> Result<(), Error> {
+        match self.value.take() {
+            Some(mut value) => Deserialize::deserialize(&mut value),
+            None => Ok(()),
+        }
+    }
+
+    fn visit_newtype<T>(&mut self) -> Result<T, Error>
+        where T: Deserialize
+    {
+        match self.value.take() {
+            Some(mut value) => Deserialize::deserialize(&mut value),
+            None => Err(serde::de::Error::end_of_stream()),
+        }
+    }
+
+    fn visit_tuple<V>(&mut self, _len: usize, visitor: V) -> Result<V::Value, Error>
+        where V: serde::de::Visitor
+    {
+        match self.value.take() {
+            Some(mut value) => serde::Deserializer::deserialize(&mut value, visitor),
+            None => Err(serde::de::Error::end_of_stream()),
+        }
+    }
+
+    fn visit_struct<V>(&mut self,
+                       _fields: &'static [&'static str],
+                       visitor: V)
+                       -> Result<V::Value, Error>
+        where V: serde::de::Visitor
+    {
+        match self.value.take() {
+            Some(mut value) => serde::Deserializer::deserialize(&mut value, visitor),
+            None => Err(serde::de::Error::end_of_stream()),
+        }
+    }
+}
+
 impl PartialEq for Value {
     fn eq(&self, other: &Value) -> bool {
         match (self, other) {
             (&Value::Null, &Value::Null) => true,
             (&Value::Bool(a), &Value::Bool(b)) => a == b,
             (&Value::I64(a), &Value::I64(b)) => a == b,
-            (&Value::F64(a), &Value::F64(b)) => {
-                if a.is_nan() && b.is_nan() {
-                    // compare NaN for bitwise equality
-                    let (a, b): (i64, i64) = unsafe {
-                        (mem::transmute(a), mem::transmute(b))
-                    };
-                    a == b
-                } else {
-                    a == b
-                }
-            }
+            (&Value::F64(a), &Value::F64(b)) => a == b,
             (&Value::String(ref a), &Value::String(ref b)) => a == b,
             (&Value::Sequence(ref a), &Value::Sequence(ref b)) => a == b,
             (&Value::Mapping(ref a), &Value::Mapping(ref b)) => a == b,
+            (&Value::Tagged(ref at, ref av), &Value::Tagged(ref bt, ref bv)) => {
+                at == bt && av == bv
+            }
             _ => false,
         }
     }
@@ -336,13 +887,265 @@ impl Hash for Value {
             &Value::Null => 0.hash(state),
             &Value::Bool(b) => (1, b).hash(state),
             &Value::I64(i) => (2, i).hash(state),
-            &Value::F64(_) => {
-                // you should feel bad for using f64 as a map key
-                3.hash(state);
-            }
+            &Value::F64(f) => (3, f).hash(state),
             &Value::String(ref s) => (4, s).hash(state),
             &Value::Sequence(ref seq) => (5, seq).hash(state),
             &Value::Mapping(ref map) => (6, map).hash(state),
+            &Value::Tagged(ref tag, ref value) => (7, tag, value).hash(state),
+        }
+    }
+}
+
+/// A `RawValue` captures the verbatim YAML source of a fragment of a document,
+/// modelled on `serde_json::value::RawValue`. It lets a program defer or skip
+/// decoding part of a document into a [`Value`] — a comment-laden or
+/// unrecognized section — and
+/// re-emit it byte-for-byte, which is exactly what a proxy/pass-through tool
+/// that edits one key while leaving the rest untouched needs.
+///
+/// Construct one with [`RawValue::from_str`]: the original text — including its
+/// comments, indentation and scalar style — is retained and handed back
+/// unchanged by [`RawValue::get`] / `Display`, and [`RawValue::parse`] decodes
+/// it to a [`Value`] only when needed.
+///
+/// # Scope
+///
+/// serde_json's `RawValue` also captures a fragment *by position* when it
+/// appears as a struct field, because its `Deserializer` special-cases a
+/// private token to hand back the exact source slice. This crate's serde
+/// pipeline carries no source spans and exposes no such raw-text escape hatch,
+/// so `RawValue` deliberately does **not** implement `Serialize`/`Deserialize`:
+/// a verbatim guarantee that silently canonicalized through the serde path
+/// would be worse than none. The byte-for-byte contract holds on the explicit
+/// [`from_str`](RawValue::from_str) / [`get`](RawValue::get) /
+/// [`Display`] API, which is the passthrough use case the feature exists for;
+/// round-trip as a serde field is out of scope against this serde version.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RawValue {
+    source: String,
+}
+
+impl RawValue {
+    /// Capture a fragment from its YAML source verbatim, deferring the parse.
+    ///
+    /// The source is validated as well-formed YAML but the parsed tree is
+    /// discarded, so only the original bytes are retained. Validation is
+    /// independent of the single-document policy of [`from_str`]: a captured
+    /// fragment may span any well-formed YAML, including a multi-document
+    /// stream, since `RawValue` never reparses it for passthrough.
+    pub fn from_str(source: &str) -> Result<Self, Error> {
+        try!(YamlLoader::load_from_str(source).map_err(|e| {
+            <Error as serde::de::Error>::custom(format!("{}", e))
+        }));
+        Ok(RawValue { source: source.to_owned() })
+    }
+
+    /// The captured fragment's verbatim YAML source.
+    pub fn get(&self) -> &str {
+        &self.source
+    }
+
+    /// Parse the captured fragment into a [`Value`] on demand.
+    pub fn parse(&self) -> Result<Value, Error> {
+        from_str(&self.source)
+    }
+}
+
+impl ::std::fmt::Display for RawValue {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    use super::{from_str, from_value, to_value, OrderedF64, Value};
+
+    fn string(s: &str) -> Value {
+        Value::String(s.to_owned())
+    }
+
+    fn hash_of<T: Hash>(value: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    // ---- chunk0-4: `OrderedF64` is a sound, consistent key ----
+
+    #[test]
+    fn ordered_f64_folds_negative_zero() {
+        let pos = OrderedF64::new(0.0);
+        let neg = OrderedF64::new(-0.0);
+        assert_eq!(pos, neg);
+        assert_eq!(hash_of(&pos), hash_of(&neg));
+    }
+
+    #[test]
+    fn ordered_f64_nan_is_self_equal_and_greatest() {
+        use std::cmp::Ordering;
+        let nan = OrderedF64::new(::std::f64::NAN);
+        assert_eq!(nan, nan);
+        assert_eq!(hash_of(&nan), hash_of(&nan));
+        assert_eq!(nan.cmp(&OrderedF64::new(1.0)), Ordering::Greater);
+    }
+
+    #[test]
+    fn float_map_keys_are_distinct_buckets() {
+        use linked_hash_map::LinkedHashMap;
+        let mut map: LinkedHashMap<Value, i32> = LinkedHashMap::new();
+        map.insert(Value::F64(OrderedF64::new(1.0)), 1);
+        map.insert(Value::F64(OrderedF64::new(2.0)), 2);
+        map.insert(Value::F64(OrderedF64::new(-0.0)), 3);
+        // `-0.0` collapses onto `0.0`; the two finite values stay separate.
+        map.insert(Value::F64(OrderedF64::new(0.0)), 4);
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get(&Value::F64(OrderedF64::new(0.0))), Some(&4));
+    }
+
+    // ---- chunk0-1: direct `Deserializer` enum/option routing ----
+
+    #[test]
+    fn option_routes_through_null() {
+        let present: Option<i64> = from_value(Value::I64(7)).unwrap();
+        assert_eq!(present, Some(7));
+        let absent: Option<i64> = from_value(Value::Null).unwrap();
+        assert_eq!(absent, None);
+    }
+
+    #[test]
+    fn enum_decodes_from_map_and_bare_string() {
+        use serde::Deserialize;
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        enum E {
+            Unit,
+            Newtype(i64),
+        }
+
+        // Bare string -> unit variant.
+        assert_eq!(from_value::<E>(string("Unit")).unwrap(), E::Unit);
+        // Single-entry map -> externally-tagged variant with payload.
+        let mut map = super::Mapping::new();
+        map.insert(string("Newtype"), Value::I64(42));
+        assert_eq!(from_value::<E>(Value::Mapping(map)).unwrap(), E::Newtype(42));
+    }
+
+    // ---- chunk0-2: anchors, aliases and `<<` merge keys ----
+
+    #[test]
+    fn alias_expands_to_referenced_value() {
+        let doc = from_str("first: &a [1, 2]\nsecond: *a\n").unwrap();
+        let map = doc.as_mapping().unwrap();
+        let expected = Value::Sequence(vec![Value::I64(1), Value::I64(2)]);
+        assert_eq!(map.get(&string("first")), Some(&expected));
+        assert_eq!(map.get(&string("second")), Some(&expected));
+    }
+
+    #[test]
+    fn merge_key_splices_without_overriding_present_keys() {
+        let source = "\
+base: &base
+  host: localhost
+  port: 80
+override:
+  <<: *base
+  port: 8080
+";
+        let doc = from_str(source).unwrap();
+        let over = doc.as_mapping().unwrap().get(&string("override")).unwrap();
+        let over = over.as_mapping().unwrap();
+        // Key already present wins over the merged entry...
+        assert_eq!(over.get(&string("port")), Some(&Value::I64(8080)));
+        // ...while absent keys are pulled in from the anchor.
+        assert_eq!(over.get(&string("host")), Some(&string("localhost")));
+    }
+
+    #[test]
+    fn plain_scalar_schema_matches_yaml_rust() {
+        // Delegating to `Yaml::from_str` resolves the same scalars the
+        // `From<Yaml>` bridge does, rather than falling back to strings.
+        assert_eq!(from_str("0x1f").unwrap(), Value::I64(0x1f));
+        assert_eq!(from_str("0o17").unwrap(), Value::I64(0o17));
+        assert_eq!(from_str("quoted: '0x1f'").unwrap()
+                       .as_mapping().unwrap()
+                       .get(&string("quoted")),
+                   Some(&string("0x1f")));
+    }
+
+    #[test]
+    fn multi_document_stream_is_an_error() {
+        // A single-document API must signal, not silently drop, extra docs.
+        assert!(from_str("---\nfoo: 1\n---\nbar: 2\n").is_err());
+        assert_eq!(from_str("").unwrap(), Value::Null);
+    }
+
+    // ---- chunk0-3: explicit tags drive enum dispatch ----
+
+    #[test]
+    fn application_tag_becomes_tagged_value() {
+        let doc = from_str("!Newtype 42").unwrap();
+        assert_eq!(doc,
+                   Value::Tagged("Newtype".to_owned(), Box::new(Value::I64(42))));
+    }
+
+    #[test]
+    fn tagged_value_decodes_as_enum_variant() {
+        use serde::Deserialize;
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        enum E {
+            Newtype(i64),
         }
+
+        let tagged = Value::Tagged("Newtype".to_owned(), Box::new(Value::I64(42)));
+        assert_eq!(from_value::<E>(tagged).unwrap(), E::Newtype(42));
+    }
+
+    #[test]
+    fn enum_serialize_round_trips_through_value() {
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        enum E {
+            Newtype(i64),
+        }
+
+        // Without a `!Variant` tag node in yaml_rust, an enum variant
+        // serializes to the externally-tagged `{Variant: payload}` map; that
+        // shape decodes straight back to the enum, so the value round-trips.
+        let value = to_value(&E::Newtype(42));
+        assert_eq!(from_value::<E>(value).unwrap(), E::Newtype(42));
+    }
+
+    // ---- chunk0-5: `RawValue` preserves its source verbatim ----
+
+    #[test]
+    fn raw_value_round_trips_source_byte_for_byte() {
+        use super::RawValue;
+        let source = "# keep this comment\nkey:   'quoted'   # trailing\nlist:\n  - 1\n";
+        let raw = RawValue::from_str(source).unwrap();
+        // get()/Display hand back the exact bytes, comments and all...
+        assert_eq!(raw.get(), source);
+        assert_eq!(raw.to_string(), source);
+        // ...and the fragment can still be decoded on demand.
+        let value = raw.parse().unwrap();
+        assert_eq!(value.as_mapping().unwrap().get(&string("key")),
+                   Some(&string("quoted")));
+    }
+
+    #[test]
+    fn raw_value_leaves_captured_section_untouched() {
+        use super::RawValue;
+        // The passthrough use case: splice an edited key ahead of a verbatim,
+        // comment-laden section captured as a RawValue.
+        let section = "legacy:\n  # do not reformat\n  a: 1\n  b: 2\n";
+        let raw = RawValue::from_str(section).unwrap();
+        let rendered = format!("edited: true\n{}", raw);
+        assert!(rendered.contains("# do not reformat"));
+        assert!(rendered.ends_with(section));
     }
 }